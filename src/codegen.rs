@@ -0,0 +1,514 @@
+//! SCPD-to-Rust code generation.
+//!
+//! Turns a parsed [`SCPD`] into a strongly typed service client module: one
+//! enum per `StateVariable` that has an `allowed_value_list`, and one
+//! request struct per `Action` with fields drawn from `input_arguments()`
+//! and the `data_type_str_*` mappings. Intended for use from a `build.rs`.
+
+use crate::scpd::{Action, Argument, StateVariable, SCPD};
+use std::io::{self, Write};
+
+/// Generates a module for `scpd` and returns it as a `String`, e.g. for a
+/// build script to write under `OUT_DIR`.
+pub fn generate(scpd: SCPD) -> String {
+    let mut buffer = Vec::new();
+    write(scpd, &mut buffer).expect("writing generated source to a Vec<u8> never fails");
+    String::from_utf8(buffer).expect("generated source is valid UTF-8")
+}
+
+/// Same as [`generate`], but writes the module directly to `out`.
+pub fn write<W: Write>(scpd: SCPD, out: &mut W) -> io::Result<()> {
+    let (urn, state_variables, actions) = scpd.destructure();
+
+    writeln!(out, "// Generated from {} by rupnp's codegen module.", urn)?;
+    writeln!(out, "#![allow(non_snake_case, non_camel_case_types)]")?;
+    writeln!(out)?;
+
+    for state_variable in &state_variables {
+        if let Some(allowed) = state_variable.allowed_values() {
+            write_allowed_value_enum(out, state_variable.name(), allowed)?;
+        }
+    }
+
+    for action in &actions {
+        write_action_request(out, action, &state_variables)?;
+    }
+
+    Ok(())
+}
+
+fn write_allowed_value_enum<W: Write>(out: &mut W, name: &str, allowed: &[String]) -> io::Result<()> {
+    writeln!(
+        out,
+        "#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]"
+    )?;
+    writeln!(out, "pub enum {} {{", name)?;
+    for variant in allowed {
+        writeln!(out, "    {},", variant_name(variant))?;
+    }
+    writeln!(out, "}}")?;
+    writeln!(out)?;
+
+    // Round-trips through the wire string a device actually sends, which
+    // `variant_name` may have reshaped into a valid identifier.
+    writeln!(out, "impl {} {{", name)?;
+    writeln!(out, "    pub fn to_wire_str(&self) -> &'static str {{")?;
+    writeln!(out, "        match self {{")?;
+    for variant in allowed {
+        writeln!(out, "            {}::{} => {:?},", name, variant_name(variant), variant)?;
+    }
+    writeln!(out, "        }}")?;
+    writeln!(out, "    }}")?;
+    writeln!(out)?;
+    writeln!(out, "    pub fn from_wire_str(raw: &str) -> Option<Self> {{")?;
+    writeln!(out, "        match raw {{")?;
+    for variant in allowed {
+        writeln!(out, "            {:?} => Some({}::{}),", variant, name, variant_name(variant))?;
+    }
+    writeln!(out, "            _ => None,")?;
+    writeln!(out, "        }}")?;
+    writeln!(out, "    }}")?;
+    writeln!(out, "}}")?;
+    writeln!(out)
+}
+
+fn write_action_request<W: Write>(
+    out: &mut W,
+    action: &Action,
+    state_variables: &[StateVariable],
+) -> io::Result<()> {
+    writeln!(out, "#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]")?;
+    writeln!(out, "pub struct {}Request {{", action.name())?;
+    for argument in action.input_arguments() {
+        writeln!(
+            out,
+            "    pub {}: {},",
+            argument.name(),
+            field_type(argument, state_variables)
+        )?;
+    }
+    writeln!(out, "}}")?;
+    writeln!(out)?;
+
+    let outputs: Vec<&Argument> = action.output_arguments().collect();
+    let return_type = match outputs.as_slice() {
+        [] => "()".to_string(),
+        [single] => field_type(single, state_variables),
+        many => format!(
+            "({})",
+            many.iter()
+                .map(|arg| field_type(arg, state_variables))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+    writeln!(out, "#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]")?;
+    writeln!(out, "pub struct {}Response(pub {});", action.name(), return_type)?;
+    writeln!(out)?;
+
+    write_action_call(out, action, &outputs, state_variables)
+}
+
+/// Emits an `impl {Action}Request { pub async fn call(...) }` that builds the
+/// argument list from the request's fields and invokes `Action::call`,
+/// decoding the output arguments back into an `{Action}Response`.
+fn write_action_call<W: Write>(
+    out: &mut W,
+    action: &Action,
+    outputs: &[&Argument],
+    state_variables: &[StateVariable],
+) -> io::Result<()> {
+    let related = |argument: &Argument| {
+        state_variables
+            .iter()
+            .find(|sv| sv.name() == argument.related_state_variable())
+    };
+
+    writeln!(out, "impl {}Request {{", action.name())?;
+    writeln!(out, "    /// Invokes the `{}` action over `transport`.", action.name())?;
+    writeln!(out, "    pub async fn call<T: upnp::transport::Transport>(")?;
+    writeln!(out, "        &self,")?;
+    writeln!(out, "        transport: &T,")?;
+    writeln!(out, "        control_url: hyper::Uri,")?;
+    writeln!(out, "        urn: &str,")?;
+    writeln!(out, "        action: &upnp::scpd::Action,")?;
+    writeln!(out, "        state_variables: &[upnp::scpd::StateVariable],")?;
+    writeln!(out, "    ) -> Result<{}Response, upnp::Error> {{", action.name())?;
+    // Each entry is an `Option<(&str, Value)>` so an absent `optional` field
+    // (`field_type` wraps those in `Option<T>`) is left out of the call
+    // entirely, rather than trying to build a `Value` from a `None`.
+    writeln!(out, "        let arguments: Vec<(&str, upnp::value::Value)> = vec![")?;
+    for argument in action.input_arguments() {
+        let state_variable = related(argument);
+        let field = format!("self.{}", argument.name());
+        if state_variable.map_or(false, |sv| sv.optional()) {
+            writeln!(
+                out,
+                "            {}.as_ref().map(|v| ({:?}, {})),",
+                field,
+                argument.name(),
+                value_constructor(state_variable, "v")
+            )?;
+        } else {
+            writeln!(
+                out,
+                "            Some(({:?}, {})),",
+                argument.name(),
+                value_constructor(state_variable, &field)
+            )?;
+        }
+    }
+    writeln!(out, "        ]")?;
+    writeln!(out, "        .into_iter()")?;
+    writeln!(out, "        .flatten()")?;
+    writeln!(out, "        .collect();")?;
+    writeln!(
+        out,
+        "        let mut outputs = action.call(transport, control_url, urn, state_variables, &arguments).await?;"
+    )?;
+
+    match outputs {
+        [] => {
+            writeln!(out, "        let _ = outputs;")?;
+            writeln!(out, "        Ok({}Response(()))", action.name())?;
+        }
+        [single] => {
+            writeln!(
+                out,
+                "        Ok({}Response({}))",
+                action.name(),
+                output_decode(single, related(single))
+            )?;
+        }
+        many => {
+            writeln!(out, "        Ok({}Response((", action.name())?;
+            for argument in many {
+                writeln!(out, "            {},", output_decode(argument, related(argument)))?;
+            }
+            writeln!(out, "        )))")?;
+        }
+    }
+
+    writeln!(out, "    }}")?;
+    writeln!(out, "}}")?;
+    writeln!(out)
+}
+
+/// Builds an expression that removes `argument`'s output value from the
+/// `outputs` map and decodes it into `related`'s Rust type. An `optional`
+/// state variable decodes to `Option<T>` (via `Option::map`/`transpose`)
+/// instead of failing when the device omits the argument.
+fn output_decode(argument: &Argument, related: Option<&StateVariable>) -> String {
+    if related.map_or(false, |sv| sv.optional()) {
+        format!(
+            "outputs.remove({:?}).map(|value| -> Result<_, upnp::Error> {{ Ok({}) }}).transpose()?",
+            argument.name(),
+            value_extractor(related, "value")
+        )
+    } else {
+        format!(
+            "{{ let value = outputs.remove({:?}).ok_or(upnp::Error::InvalidResponse)?; {} }}",
+            argument.name(),
+            value_extractor(related, "value")
+        )
+    }
+}
+
+/// Builds a `upnp::value::Value` expression wrapping `field_expr` for
+/// `related`'s data type.
+fn value_constructor(related: Option<&StateVariable>, field_expr: &str) -> String {
+    match related {
+        Some(state_variable) if state_variable.allowed_values().is_some() => {
+            format!("upnp::value::Value::Str({}.to_wire_str().to_string())", field_expr)
+        }
+        Some(state_variable) if state_variable.value_variant() == "TimeTz" => format!(
+            "upnp::value::Value::TimeTz({0}.clone().0, {0}.clone().1)",
+            field_expr
+        ),
+        Some(state_variable) => format!(
+            "upnp::value::Value::{}({}.clone())",
+            state_variable.value_variant(),
+            field_expr
+        ),
+        None => format!("upnp::value::Value::Str({}.clone())", field_expr),
+    }
+}
+
+/// Builds an expression that unwraps `value_expr` (a `upnp::value::Value`)
+/// back into `related`'s Rust type, bailing out with `Error::InvalidResponse`
+/// on a variant mismatch.
+fn value_extractor(related: Option<&StateVariable>, value_expr: &str) -> String {
+    match related {
+        Some(state_variable) if state_variable.allowed_values().is_some() => format!(
+            "match {0} {{ upnp::value::Value::Str(s) => {1}::from_wire_str(&s).ok_or(upnp::Error::InvalidResponse)?, _ => return Err(upnp::Error::InvalidResponse) }}",
+            value_expr,
+            state_variable.name()
+        ),
+        Some(state_variable) if state_variable.value_variant() == "TimeTz" => format!(
+            "match {0} {{ upnp::value::Value::TimeTz(t, o) => (t, o), _ => return Err(upnp::Error::InvalidResponse) }}",
+            value_expr
+        ),
+        Some(state_variable) => format!(
+            "match {0} {{ upnp::value::Value::{1}(v) => v, _ => return Err(upnp::Error::InvalidResponse) }}",
+            value_expr,
+            state_variable.value_variant()
+        ),
+        None => format!(
+            "match {0} {{ upnp::value::Value::Str(v) => v, _ => return Err(upnp::Error::InvalidResponse) }}",
+            value_expr
+        ),
+    }
+}
+
+/// The Rust type for `argument`'s related state variable, wrapped in
+/// `Option` when that state variable is marked `optional`.
+fn field_type(argument: &Argument, state_variables: &[StateVariable]) -> String {
+    let related = state_variables
+        .iter()
+        .find(|sv| sv.name() == argument.related_state_variable());
+
+    match related {
+        Some(state_variable) if state_variable.optional() => {
+            format!("Option<{}>", state_variable.data_type_str_input())
+        }
+        Some(state_variable) => state_variable.data_type_str_input().to_string(),
+        None => "String".to_string(),
+    }
+}
+
+/// Turns an allowed-value string like `"OK"` or `"not implemented"` into a
+/// valid, idiomatically-cased enum variant name.
+fn variant_name(raw: &str) -> String {
+    raw.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().collect::<String>() + chars.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One state variable with an allowed-value list (exercising the enum +
+    /// `to_wire_str`/`from_wire_str` emission) and one action with an `in`
+    /// argument bound to it and an `out` argument bound to a plain `ui4`.
+    fn scpd_fixture() -> SCPD {
+        let mut scpd: SCPD = serde_xml_rs::from_str(
+            r#"<scpd>
+                <serviceStateTable>
+                    <stateVariable>
+                        <name>A_ARG_TYPE_Channel</name>
+                        <dataType>string</dataType>
+                        <allowedValueList>
+                            <allowedValue>Master</allowedValue>
+                            <allowedValue>LF</allowedValue>
+                        </allowedValueList>
+                    </stateVariable>
+                    <stateVariable>
+                        <name>Volume</name>
+                        <dataType>ui4</dataType>
+                    </stateVariable>
+                </serviceStateTable>
+                <actionList>
+                    <action>
+                        <name>GetVolume</name>
+                        <argumentList>
+                            <argument>
+                                <name>Channel</name>
+                                <direction>in</direction>
+                                <relatedStateVariable>A_ARG_TYPE_Channel</relatedStateVariable>
+                            </argument>
+                            <argument>
+                                <name>CurrentVolume</name>
+                                <direction>out</direction>
+                                <relatedStateVariable>Volume</relatedStateVariable>
+                            </argument>
+                        </argumentList>
+                    </action>
+                </actionList>
+            </scpd>"#,
+        )
+        .expect("fixture SCPD parses");
+        scpd.set_urn("urn:schemas-upnp-org:service:RenderingControl:1".to_string());
+        scpd
+    }
+
+    #[test]
+    fn generates_allowed_value_enum_and_action_client() {
+        let generated = generate(scpd_fixture());
+
+        let expected = "\
+// Generated from urn:schemas-upnp-org:service:RenderingControl:1 by rupnp's codegen module.
+#![allow(non_snake_case, non_camel_case_types)]
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Channel {
+    Master,
+    LF,
+}
+
+impl Channel {
+    pub fn to_wire_str(&self) -> &'static str {
+        match self {
+            Channel::Master => \"Master\",
+            Channel::LF => \"LF\",
+        }
+    }
+
+    pub fn from_wire_str(raw: &str) -> Option<Self> {
+        match raw {
+            \"Master\" => Some(Channel::Master),
+            \"LF\" => Some(Channel::LF),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct GetVolumeRequest {
+    pub Channel: Channel,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct GetVolumeResponse(pub u32);
+
+impl GetVolumeRequest {
+    /// Invokes the `GetVolume` action over `transport`.
+    pub async fn call<T: upnp::transport::Transport>(
+        &self,
+        transport: &T,
+        control_url: hyper::Uri,
+        urn: &str,
+        action: &upnp::scpd::Action,
+        state_variables: &[upnp::scpd::StateVariable],
+    ) -> Result<GetVolumeResponse, upnp::Error> {
+        let arguments: Vec<(&str, upnp::value::Value)> = vec![
+            Some((\"Channel\", upnp::value::Value::Str(self.Channel.to_wire_str().to_string()))),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        let mut outputs = action.call(transport, control_url, urn, state_variables, &arguments).await?;
+        Ok(GetVolumeResponse({ let value = outputs.remove(\"CurrentVolume\").ok_or(upnp::Error::InvalidResponse)?; match value { upnp::value::Value::UI4(v) => v, _ => return Err(upnp::Error::InvalidResponse) } }))
+    }
+}
+
+";
+
+        assert_eq!(generated, expected);
+    }
+
+    /// An `<optional/>` state variable used as both an input and an output
+    /// argument: the generated fields are `Option<T>`, the argument is left
+    /// out of the call entirely when absent, and a missing output decodes to
+    /// `None` instead of `Error::InvalidResponse`.
+    fn scpd_fixture_with_optional() -> SCPD {
+        let mut scpd: SCPD = serde_xml_rs::from_str(
+            r#"<scpd>
+                <serviceStateTable>
+                    <stateVariable>
+                        <name>A_ARG_TYPE_Channel</name>
+                        <dataType>string</dataType>
+                        <allowedValueList>
+                            <allowedValue>Master</allowedValue>
+                        </allowedValueList>
+                        <optional/>
+                    </stateVariable>
+                    <stateVariable>
+                        <name>PeakVolume</name>
+                        <dataType>ui4</dataType>
+                        <optional/>
+                    </stateVariable>
+                </serviceStateTable>
+                <actionList>
+                    <action>
+                        <name>GetVolumeEx</name>
+                        <argumentList>
+                            <argument>
+                                <name>Channel</name>
+                                <direction>in</direction>
+                                <relatedStateVariable>A_ARG_TYPE_Channel</relatedStateVariable>
+                            </argument>
+                            <argument>
+                                <name>PeakVolume</name>
+                                <direction>out</direction>
+                                <relatedStateVariable>PeakVolume</relatedStateVariable>
+                            </argument>
+                        </argumentList>
+                    </action>
+                </actionList>
+            </scpd>"#,
+        )
+        .expect("fixture SCPD parses");
+        scpd.set_urn("urn:schemas-upnp-org:service:RenderingControl:1".to_string());
+        scpd
+    }
+
+    #[test]
+    fn generates_option_wrapped_fields_for_optional_state_variables() {
+        let generated = generate(scpd_fixture_with_optional());
+
+        let expected = "\
+// Generated from urn:schemas-upnp-org:service:RenderingControl:1 by rupnp's codegen module.
+#![allow(non_snake_case, non_camel_case_types)]
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Channel {
+    Master,
+}
+
+impl Channel {
+    pub fn to_wire_str(&self) -> &'static str {
+        match self {
+            Channel::Master => \"Master\",
+        }
+    }
+
+    pub fn from_wire_str(raw: &str) -> Option<Self> {
+        match raw {
+            \"Master\" => Some(Channel::Master),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct GetVolumeExRequest {
+    pub Channel: Option<Channel>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct GetVolumeExResponse(pub Option<u32>);
+
+impl GetVolumeExRequest {
+    /// Invokes the `GetVolumeEx` action over `transport`.
+    pub async fn call<T: upnp::transport::Transport>(
+        &self,
+        transport: &T,
+        control_url: hyper::Uri,
+        urn: &str,
+        action: &upnp::scpd::Action,
+        state_variables: &[upnp::scpd::StateVariable],
+    ) -> Result<GetVolumeExResponse, upnp::Error> {
+        let arguments: Vec<(&str, upnp::value::Value)> = vec![
+            self.Channel.as_ref().map(|v| (\"Channel\", upnp::value::Value::Str(v.to_wire_str().to_string()))),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        let mut outputs = action.call(transport, control_url, urn, state_variables, &arguments).await?;
+        Ok(GetVolumeExResponse(outputs.remove(\"PeakVolume\").map(|value| -> Result<_, upnp::Error> { Ok(match value { upnp::value::Value::UI4(v) => v, _ => return Err(upnp::Error::InvalidResponse) }) }).transpose()?))
+    }
+}
+
+";
+
+        assert_eq!(generated, expected);
+    }
+}
@@ -0,0 +1,92 @@
+//! The crate's error type.
+
+use std::fmt;
+use std::num::{ParseFloatError, ParseIntError};
+
+/// Everything that can go wrong fetching, parsing, validating, or invoking
+/// against a UPnP service.
+#[derive(Debug)]
+pub enum Error {
+    /// The underlying HTTP request failed.
+    NetworkError(hyper::Error),
+    /// An HTTP request couldn't be built, e.g. an invalid header value.
+    InvalidRequest(hyper::http::Error),
+    /// A required response header (e.g. GENA's `SID`) was missing.
+    MissingHeader(String),
+    /// An SCPD document failed to parse.
+    Xml(serde_xml_rs::Error),
+    /// A wire-format string didn't parse as its declared `DataType`.
+    InvalidValue(String),
+    /// A SOAP response was missing, malformed, or didn't contain an expected
+    /// output argument.
+    InvalidResponse,
+    /// `Action::call` was given an argument its `argumentList` doesn't declare.
+    UnexpectedArgument(String),
+    /// `Action::call` was missing a required input argument.
+    MissingArgument(String),
+    /// An argument's `relatedStateVariable` doesn't name a known state variable.
+    UnknownStateVariable(String),
+    /// An argument's value doesn't match its state variable's `dataType`.
+    ArgumentTypeMismatch { expected: String, found: String },
+    /// An argument's value falls outside its state variable's `allowedValueRange`.
+    ArgumentOutOfRange { name: String, value: i64, min: i64, max: i64 },
+    /// An argument's value isn't one of its state variable's `allowedValueList`.
+    ArgumentNotAllowed {
+        name: String,
+        value: String,
+        allowed: Vec<String>,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NetworkError(e) => write!(f, "network error: {}", e),
+            Error::InvalidRequest(e) => write!(f, "invalid request: {}", e),
+            Error::MissingHeader(name) => write!(f, "missing response header {:?}", name),
+            Error::Xml(e) => write!(f, "failed to parse XML: {}", e),
+            Error::InvalidValue(raw) => write!(f, "invalid value: {:?}", raw),
+            Error::InvalidResponse => write!(f, "invalid or unexpected SOAP response"),
+            Error::UnexpectedArgument(name) => write!(f, "unexpected argument {:?}", name),
+            Error::MissingArgument(name) => write!(f, "missing argument {:?}", name),
+            Error::UnknownStateVariable(name) => write!(f, "unknown state variable {:?}", name),
+            Error::ArgumentTypeMismatch { expected, found } => {
+                write!(f, "expected a {} argument, found {}", expected, found)
+            }
+            Error::ArgumentOutOfRange { name, value, min, max } => {
+                write!(f, "{} = {} is out of range [{}, {}]", name, value, min, max)
+            }
+            Error::ArgumentNotAllowed { name, value, allowed } => write!(
+                f,
+                "{} = {:?} is not one of the allowed values {:?}",
+                name, value, allowed
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<hyper::Error> for Error {
+    fn from(e: hyper::Error) -> Self {
+        Error::NetworkError(e)
+    }
+}
+
+impl From<serde_xml_rs::Error> for Error {
+    fn from(e: serde_xml_rs::Error) -> Self {
+        Error::Xml(e)
+    }
+}
+
+impl From<ParseIntError> for Error {
+    fn from(e: ParseIntError) -> Self {
+        Error::InvalidValue(e.to_string())
+    }
+}
+
+impl From<ParseFloatError> for Error {
+    fn from(e: ParseFloatError) -> Self {
+        Error::InvalidValue(e.to_string())
+    }
+}
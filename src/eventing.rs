@@ -0,0 +1,439 @@
+//! GENA (General Event Notification Architecture) eventing.
+//!
+//! Lets a caller `subscribe` to a service's event URL and receive an async
+//! `Stream` of state-variable changes pushed by the device via `NOTIFY`
+//! callbacks, instead of having to poll actions for state.
+
+use crate::scpd::{Bool, DataType, StateVariable};
+use crate::transport::Transport;
+use crate::value::Value;
+use crate::Error;
+
+use futures::stream::Stream;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, HeaderMap, Method, Request, Response, Server, Uri};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::sync::{mpsc, watch};
+
+/// The multicast group UPnP devices use for multicast eventing, as defined
+/// in the GENA/UPnP Device Architecture annex.
+const MULTICAST_EVENT_ADDR: &str = "239.255.255.246:7900";
+
+/// A single state-variable change delivered by a `NOTIFY` callback or a
+/// multicast event datagram.
+#[derive(Debug, Clone)]
+pub struct StateVariableChange {
+    pub variable_name: String,
+    pub value: Value,
+}
+
+/// An async stream of [`StateVariableChange`]s for a live [`Subscription`].
+pub struct EventStream {
+    receiver: mpsc::UnboundedReceiver<StateVariableChange>,
+}
+
+impl Stream for EventStream {
+    type Item = StateVariableChange;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.receiver).poll_recv(cx)
+    }
+}
+
+/// A live GENA subscription to a service's event URL.
+///
+/// Dropping the subscription stops the background renewal task *and* the
+/// `NOTIFY`/multicast listener tasks, releasing the bound callback port and
+/// the multicast group membership, and issues a best-effort `UNSUBSCRIBE` to
+/// the device.
+pub struct Subscription {
+    sid: String,
+    shutdown: Option<watch::Sender<bool>>,
+}
+
+impl Subscription {
+    /// Subscribes to `event_url` over `transport`, accepting `NOTIFY`
+    /// callbacks on `callback_addr` and joining the multicast event group for
+    /// any `variables` flagged `multicast`.
+    ///
+    /// `variables` with `send_events_attribute` set to [`Bool::No`] never
+    /// appear on the returned stream.
+    pub async fn subscribe<T: Transport + Clone + 'static>(
+        transport: T,
+        event_url: Uri,
+        callback_addr: SocketAddr,
+        variables: Vec<StateVariable>,
+    ) -> Result<(Self, EventStream), Error> {
+        // `send_events_attribute == No` opts a variable out of eventing
+        // entirely, regardless of `multicast`; apply that filter first, then
+        // split what's left between the multicast group and the unicast
+        // NOTIFY callback so each variable is only ever sourced from one
+        // channel.
+        let eventable: Vec<&StateVariable> = variables
+            .iter()
+            .filter(|v| matches!(v.send_events_attribute(), Bool::Yes))
+            .collect();
+        let multicast_wanted: HashMap<String, DataType> = eventable
+            .iter()
+            .filter(|v| matches!(v.multicast(), Bool::Yes))
+            .map(|v| (v.name().to_string(), *v.data_type()))
+            .collect();
+        let wanted: HashMap<String, DataType> = eventable
+            .iter()
+            .map(|v| (v.name().to_string(), *v.data_type()))
+            .filter(|(name, _)| !multicast_wanted.contains_key(name))
+            .collect();
+
+        // Bind (and thus actually claim) the callback port *before* telling the
+        // device to start NOTIFYing it, so a device that NOTIFYs immediately
+        // after acking the SUBSCRIBE can't race the listener coming up, and a
+        // taken port fails the subscribe instead of silently dropping events.
+        let callback_listener = Server::try_bind(&callback_addr).map_err(Error::NetworkError)?;
+
+        let callback = format!("<http://{}/>", callback_addr);
+        let headers = [
+            ("NT".to_string(), "upnp:event".to_string()),
+            ("CALLBACK".to_string(), callback),
+            ("TIMEOUT".to_string(), "Second-1800".to_string()),
+        ];
+        let header_refs: Vec<(&str, String)> = headers
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.clone()))
+            .collect();
+        let (response_headers, _body) = transport
+            .request_with_headers(
+                Method::from_bytes(b"SUBSCRIBE").expect("SUBSCRIBE is a valid token"),
+                event_url.clone(),
+                &header_refs,
+                Vec::new(),
+            )
+            .await?;
+        let sid = required_header(&response_headers, "SID")?;
+        let timeout = parse_timeout(optional_header(&response_headers, "TIMEOUT"));
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+
+        tokio::spawn(serve_notify_listener(
+            callback_listener,
+            wanted.clone(),
+            tx.clone(),
+            shutdown_rx.clone(),
+        ));
+        if !multicast_wanted.is_empty() {
+            tokio::spawn(run_multicast_listener(
+                multicast_wanted,
+                tx,
+                shutdown_rx.clone(),
+            ));
+        }
+        tokio::spawn(renew_and_unsubscribe(
+            transport,
+            event_url,
+            sid.clone(),
+            timeout,
+            shutdown_rx,
+        ));
+
+        Ok((
+            Subscription {
+                sid,
+                shutdown: Some(shutdown_tx),
+            },
+            EventStream { receiver: rx },
+        ))
+    }
+
+    /// The subscription ID (`SID`) the device assigned to this subscription.
+    pub fn sid(&self) -> &str {
+        &self.sid
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        // All three background tasks (renewal, NOTIFY listener, multicast
+        // listener) hold a receiver cloned from this sender; tripping it is
+        // enough to make the renewal task issue UNSUBSCRIBE and all three
+        // exit, releasing the callback port and multicast membership.
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(true);
+        }
+    }
+}
+
+fn required_header(headers: &HeaderMap, name: &str) -> Result<String, Error> {
+    optional_header(headers, name).ok_or_else(|| Error::MissingHeader(name.to_string()))
+}
+
+fn optional_header(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers.get(name).and_then(|v| v.to_str().ok()).map(str::to_string)
+}
+
+/// Parses a GENA `TIMEOUT` header of the form `Second-1800`, falling back to
+/// a conservative default when the device declines to give one (`Second-infinite`
+/// or a malformed value).
+fn parse_timeout(header: Option<String>) -> Duration {
+    const DEFAULT: Duration = Duration::from_secs(1800);
+    header
+        .as_deref()
+        .and_then(|h| h.strip_prefix("Second-"))
+        .and_then(|secs| secs.parse::<u64>().ok())
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT)
+}
+
+async fn renew_and_unsubscribe<T: Transport>(
+    transport: T,
+    event_url: Uri,
+    sid: String,
+    mut timeout: Duration,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    loop {
+        // Renew well before the device's stated timeout to tolerate clock
+        // skew and network latency on the renewal round-trip. Recomputed
+        // every iteration since a renewal can grant a different timeout than
+        // the one we asked for.
+        let renew_every = timeout.mul_f32(0.8);
+
+        tokio::select! {
+            _ = tokio::time::sleep(renew_every) => {
+                let requested = format!("Second-{}", timeout.as_secs());
+                let headers = [("SID".to_string(), sid.clone()), ("TIMEOUT".to_string(), requested)];
+                let header_refs: Vec<(&str, String)> = headers.iter().map(|(n, v)| (n.as_str(), v.clone())).collect();
+                if let Ok((response_headers, _)) = transport
+                    .request_with_headers(
+                        Method::from_bytes(b"SUBSCRIBE").expect("SUBSCRIBE is a valid token"),
+                        event_url.clone(),
+                        &header_refs,
+                        Vec::new(),
+                    )
+                    .await
+                {
+                    timeout = parse_timeout(optional_header(&response_headers, "TIMEOUT"));
+                }
+            }
+            _ = shutdown.changed() => break,
+        }
+    }
+
+    let headers = [("SID".to_string(), sid)];
+    let header_refs: Vec<(&str, String)> = headers.iter().map(|(n, v)| (n.as_str(), v.clone())).collect();
+    let _ = transport
+        .request(
+            Method::from_bytes(b"UNSUBSCRIBE").expect("UNSUBSCRIBE is a valid token"),
+            event_url,
+            &header_refs,
+            Vec::new(),
+        )
+        .await;
+}
+
+/// Serves `NOTIFY` callbacks on an already-bound listener. The bind itself
+/// happens in `subscribe`, before the `SUBSCRIBE` request goes out, so a
+/// failure to claim the callback port is reported to the caller instead of
+/// silently dropping events. Stops (and releases the bound port) as soon as
+/// `shutdown` fires, i.e. when the owning `Subscription` is dropped.
+async fn serve_notify_listener(
+    listener: hyper::server::Builder<hyper::server::conn::AddrIncoming>,
+    wanted: HashMap<String, DataType>,
+    tx: mpsc::UnboundedSender<StateVariableChange>,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    let make_service = make_service_fn(move |_conn| {
+        let wanted = wanted.clone();
+        let tx = tx.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req: Request<Body>| {
+                let wanted = wanted.clone();
+                let tx = tx.clone();
+                async move {
+                    if req.method().as_str() == "NOTIFY" {
+                        if let Ok(body) = hyper::body::to_bytes(req.into_body()).await {
+                            for change in parse_propertyset(&body, &wanted) {
+                                let _ = tx.send(change);
+                            }
+                        }
+                    }
+                    Ok::<_, Infallible>(Response::new(Body::empty()))
+                }
+            }))
+        }
+    });
+
+    let _ = listener
+        .serve(make_service)
+        .with_graceful_shutdown(async move {
+            let _ = shutdown.changed().await;
+        })
+        .await;
+}
+
+/// Joins the multicast event group and forwards datagrams until `shutdown`
+/// fires, at which point the socket (and its multicast membership) is
+/// dropped.
+async fn run_multicast_listener(
+    wanted: HashMap<String, DataType>,
+    tx: mpsc::UnboundedSender<StateVariableChange>,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    use std::net::{IpAddr, Ipv4Addr, SocketAddrV4};
+    use tokio::net::UdpSocket;
+
+    let group: SocketAddr = match MULTICAST_EVENT_ADDR.parse() {
+        Ok(addr) => addr,
+        Err(_) => return,
+    };
+    let socket = match UdpSocket::bind(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, group.port())).await
+    {
+        Ok(socket) => socket,
+        Err(_) => return,
+    };
+    if let (IpAddr::V4(multicast_addr), _) = (group.ip(), ()) {
+        if socket
+            .join_multicast_v4(multicast_addr, Ipv4Addr::UNSPECIFIED)
+            .is_err()
+        {
+            return;
+        }
+    }
+
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        tokio::select! {
+            received = socket.recv(&mut buf) => {
+                let received = match received {
+                    Ok(n) => n,
+                    Err(_) => break,
+                };
+                for change in parse_propertyset(&buf[..received], &wanted) {
+                    let _ = tx.send(change);
+                }
+            }
+            _ = shutdown.changed() => break,
+        }
+    }
+}
+
+/// Parses a GENA `<e:propertyset>` body into `(variable_name, Value)` pairs,
+/// stripping `A_ARG_TYPE_` the same way the SCPD getters do. Properties not
+/// present in `data_types` (either not subscribed to, or filtered out by
+/// `send_events_attribute`/`multicast`) are dropped, as are values that fail
+/// to parse as their state variable's `DataType`.
+fn parse_propertyset(body: &[u8], data_types: &HashMap<String, DataType>) -> Vec<StateVariableChange> {
+    let document = match std::str::from_utf8(body) {
+        Ok(document) => document,
+        Err(_) => return Vec::new(),
+    };
+    let parser = xmltree::Element::parse(document.as_bytes());
+    let root = match parser {
+        Ok(root) => root,
+        Err(_) => return Vec::new(),
+    };
+
+    root.children
+        .iter()
+        .filter_map(|node| node.as_element())
+        .filter_map(|property| {
+            let variable = property.children.iter().filter_map(|n| n.as_element()).next()?;
+            let name = variable.name.trim_start_matches("A_ARG_TYPE_").to_string();
+            let data_type = *data_types.get(&name)?;
+            let raw = variable.get_text().unwrap_or_default();
+            let value = Value::from_wire_str(data_type, &raw).ok()?;
+            Some(StateVariableChange {
+                variable_name: name,
+                value,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_timeout_reads_the_second_count() {
+        assert_eq!(
+            parse_timeout(Some("Second-120".to_string())),
+            Duration::from_secs(120)
+        );
+    }
+
+    #[test]
+    fn parse_timeout_falls_back_to_the_default_when_missing_or_malformed() {
+        const DEFAULT: Duration = Duration::from_secs(1800);
+        assert_eq!(parse_timeout(None), DEFAULT);
+        assert_eq!(parse_timeout(Some("Second-infinite".to_string())), DEFAULT);
+        assert_eq!(parse_timeout(Some("garbage".to_string())), DEFAULT);
+    }
+
+    fn data_types() -> HashMap<String, DataType> {
+        [("Volume".to_string(), DataType::ui4)].into_iter().collect()
+    }
+
+    #[test]
+    fn parse_propertyset_decodes_a_wanted_property() {
+        let body = br#"<?xml version="1.0"?>
+            <e:propertyset xmlns:e="urn:schemas-upnp-org:event-1-0">
+                <e:property><Volume>42</Volume></e:property>
+            </e:propertyset>"#;
+
+        let changes = parse_propertyset(body, &data_types());
+
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].variable_name, "Volume");
+        assert_eq!(changes[0].value, Value::UI4(42));
+    }
+
+    #[test]
+    fn parse_propertyset_strips_the_a_arg_type_prefix() {
+        let mut data_types = data_types();
+        data_types.insert("Channel".to_string(), DataType::string);
+        let body = br#"<?xml version="1.0"?>
+            <e:propertyset xmlns:e="urn:schemas-upnp-org:event-1-0">
+                <e:property><A_ARG_TYPE_Channel>Master</A_ARG_TYPE_Channel></e:property>
+            </e:propertyset>"#;
+
+        let changes = parse_propertyset(body, &data_types);
+
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].variable_name, "Channel");
+        assert_eq!(changes[0].value, Value::Str("Master".to_string()));
+    }
+
+    #[test]
+    fn parse_propertyset_drops_properties_not_in_data_types() {
+        let body = br#"<?xml version="1.0"?>
+            <e:propertyset xmlns:e="urn:schemas-upnp-org:event-1-0">
+                <e:property><Volume>42</Volume></e:property>
+                <e:property><Mute>1</Mute></e:property>
+            </e:propertyset>"#;
+
+        let changes = parse_propertyset(body, &data_types());
+
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].variable_name, "Volume");
+    }
+
+    #[test]
+    fn parse_propertyset_drops_values_that_fail_to_parse() {
+        let body = br#"<?xml version="1.0"?>
+            <e:propertyset xmlns:e="urn:schemas-upnp-org:event-1-0">
+                <e:property><Volume>not-a-number</Volume></e:property>
+            </e:propertyset>"#;
+
+        assert!(parse_propertyset(body, &data_types()).is_empty());
+    }
+
+    #[test]
+    fn parse_propertyset_ignores_malformed_xml() {
+        assert!(parse_propertyset(b"<not-xml", &data_types()).is_empty());
+    }
+}
@@ -1,10 +1,10 @@
 use crate::shared::Value;
+use crate::transport::Transport;
+use crate::value::Value as ArgumentValue;
 use crate::Error;
 use getset::{Getters, Setters};
 use serde::Deserialize;
-
-use futures::compat::Future01CompatExt;
-use futures01::{Future, Stream};
+use std::collections::HashMap;
 
 #[derive(Deserialize, Debug, Getters, Setters)]
 #[serde(rename_all = "camelCase")]
@@ -65,6 +65,124 @@ impl Action {
     pub fn destructure(self) -> (String, Vec<Argument>) {
         (self.name, self.argument_list.value)
     }
+
+    /// Invokes this action over SOAP against `control_url`, validating
+    /// `arguments` against `state_variables` before sending and decoding the
+    /// response's output arguments back into a map keyed by name.
+    pub async fn call<T: Transport>(
+        &self,
+        transport: &T,
+        control_url: hyper::Uri,
+        urn: &str,
+        state_variables: &[StateVariable],
+        arguments: &[(&str, ArgumentValue)],
+    ) -> Result<HashMap<String, ArgumentValue>, Error> {
+        self.validate_arguments(state_variables, arguments)?;
+
+        let headers = [
+            ("Content-Type", "text/xml; charset=\"utf-8\"".to_string()),
+            ("SOAPACTION", format!("\"{}#{}\"", urn, self.name)),
+        ];
+        let body = transport
+            .post(
+                control_url,
+                &headers,
+                self.build_envelope(urn, arguments).into_bytes(),
+            )
+            .await?;
+
+        self.decode_response(&body, state_variables)
+    }
+
+    fn validate_arguments(
+        &self,
+        state_variables: &[StateVariable],
+        arguments: &[(&str, ArgumentValue)],
+    ) -> Result<(), Error> {
+        let mut remaining: HashMap<&str, &Argument> =
+            self.input_arguments().map(|arg| (arg.name.as_str(), arg)).collect();
+
+        for (name, value) in arguments {
+            let argument = remaining
+                .remove(name)
+                .ok_or_else(|| Error::UnexpectedArgument((*name).to_string()))?;
+            related_state_variable(argument, state_variables)?.validate(value)?;
+        }
+
+        // Inputs bound to an `optional` state variable may be left out.
+        let missing = remaining.into_values().find(|argument| {
+            related_state_variable(argument, state_variables).map_or(true, |sv| !sv.optional())
+        });
+        if let Some(missing) = missing {
+            return Err(Error::MissingArgument(missing.name.clone()));
+        }
+        Ok(())
+    }
+
+    fn build_envelope(&self, urn: &str, arguments: &[(&str, ArgumentValue)]) -> String {
+        let args: String = arguments
+            .iter()
+            .map(|(name, value)| format!("<{0}>{1}</{0}>", name, escape_xml(&value.to_wire_string())))
+            .collect();
+        format!(
+            "<?xml version=\"1.0\"?>\
+             <s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" \
+             s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\">\
+             <s:Body><u:{action} xmlns:u=\"{urn}\">{args}</u:{action}></s:Body></s:Envelope>",
+            action = self.name,
+            urn = urn,
+            args = args,
+        )
+    }
+
+    fn decode_response(
+        &self,
+        body: &[u8],
+        state_variables: &[StateVariable],
+    ) -> Result<HashMap<String, ArgumentValue>, Error> {
+        let root = xmltree::Element::parse(body).map_err(|_| Error::InvalidResponse)?;
+        let response = root
+            .get_child("Body")
+            .and_then(|body| body.children.iter().filter_map(|n| n.as_element()).next())
+            .ok_or(Error::InvalidResponse)?;
+
+        self.output_arguments()
+            .map(|argument| -> Result<_, Error> {
+                let state_variable = related_state_variable(argument, state_variables)?;
+                let element = response
+                    .children
+                    .iter()
+                    .filter_map(|n| n.as_element())
+                    .find(|el| el.name == argument.name);
+                let raw = match element {
+                    Some(el) => el.get_text().unwrap_or_default(),
+                    // An omitted `optional` output is left out of the map.
+                    None if state_variable.optional() => return Ok(None),
+                    None => return Err(Error::InvalidResponse),
+                };
+                let value = ArgumentValue::from_wire_str(*state_variable.data_type(), &raw)?;
+                state_variable.validate(&value)?;
+                Ok(Some((argument.name.clone(), value)))
+            })
+            .filter_map(Result::transpose)
+            .collect()
+    }
+}
+
+fn related_state_variable<'a>(
+    argument: &Argument,
+    state_variables: &'a [StateVariable],
+) -> Result<&'a StateVariable, Error> {
+    state_variables
+        .iter()
+        .find(|sv| sv.name() == argument.related_state_variable())
+        .ok_or_else(|| Error::UnknownStateVariable(argument.related_state_variable().to_string()))
+}
+
+fn escape_xml(raw: &str) -> String {
+    raw.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
 }
 
 #[derive(Deserialize, Getters, Debug)]
@@ -153,13 +271,27 @@ impl StateVariable {
                 DataType::i4 => "i32",
                 DataType::int => "i64",
                 /* */
+                DataType::r4 => "f32",
+                DataType::r8 => "f64",
+                DataType::number => "f64",
+                DataType::float => "f32",
+                DataType::fixed14_4 => "upnp::value::Fixed14_4",
+                /* */
                 DataType::char => "char",
                 DataType::string => "String",
                 /* */
-                DataType::boolean => "upnp::datatypes::Bool",
+                DataType::date => "chrono::NaiveDate",
+                DataType::dateTime => "chrono::NaiveDateTime",
+                DataType::dateTimeTz => "chrono::DateTime<chrono::FixedOffset>",
+                DataType::time => "chrono::NaiveTime",
+                DataType::timeTz => "(chrono::NaiveTime, chrono::FixedOffset)",
+                /* */
+                DataType::boolean => "upnp::scpd::Bool",
+                /* */
+                DataType::binBase64 => "Vec<u8>",
+                DataType::binHex => "Vec<u8>",
                 /* */
                 DataType::uri => "hyper::Uri",
-                _ => unimplemented!("{:?}", self),
             }
         }
     }
@@ -170,9 +302,82 @@ impl StateVariable {
     pub fn data_type_str_output(&self) -> &str {
         self.data_type_str()
     }
+
+    /// The `crate::value::Value` variant name matching this state variable,
+    /// e.g. `"UI4"` or `"Str"`. Lets the codegen module build and destructure
+    /// `Value`s for a generated field without hand-written conversions.
+    pub(crate) fn value_variant(&self) -> &'static str {
+        if self.allowed_values().is_some() {
+            return "Str";
+        }
+        match self.data_type() {
+            DataType::ui1 => "UI1",
+            DataType::ui2 => "UI2",
+            DataType::ui4 => "UI4",
+            DataType::ui8 => "UI8",
+            DataType::i1 => "I1",
+            DataType::i2 => "I2",
+            DataType::i4 => "I4",
+            DataType::int => "Int",
+            DataType::r4 => "R4",
+            DataType::r8 => "R8",
+            DataType::number => "Number",
+            DataType::float => "Float",
+            DataType::fixed14_4 => "Fixed14_4",
+            DataType::char => "Char",
+            DataType::string => "Str",
+            DataType::date => "Date",
+            DataType::dateTime => "DateTime",
+            DataType::dateTimeTz => "DateTimeTz",
+            DataType::time => "Time",
+            DataType::timeTz => "TimeTz",
+            DataType::boolean => "Boolean",
+            DataType::binBase64 => "BinBase64",
+            DataType::binHex => "BinHex",
+            DataType::uri => "Uri",
+        }
+    }
+
+    /// Checks `value` against this variable's `data_type`, `allowed_value_range`
+    /// and `allowed_value_list`, in that order.
+    pub fn validate(&self, value: &ArgumentValue) -> Result<(), Error> {
+        if value.data_type() != *self.data_type() {
+            return Err(Error::ArgumentTypeMismatch {
+                expected: format!("{:?}", self.data_type()),
+                found: format!("{:?}", value.data_type()),
+            });
+        }
+
+        if let Some(range) = self.allowed_value_range() {
+            if let Some(n) = value.as_i64() {
+                let (min, max, step) = (range.minimum() as i64, range.maximum() as i64, range.step() as i64);
+                if n < min || n > max || (step != 0 && (n - min) % step != 0) {
+                    return Err(Error::ArgumentOutOfRange {
+                        name: self.name().to_string(),
+                        value: n,
+                        min,
+                        max,
+                    });
+                }
+            }
+        }
+
+        if let Some(allowed) = self.allowed_values() {
+            let wire = value.to_wire_string();
+            if !allowed.iter().any(|candidate| candidate == &wire) {
+                return Err(Error::ArgumentNotAllowed {
+                    name: self.name().to_string(),
+                    value: wire,
+                    allowed: allowed.clone(),
+                });
+            }
+        }
+
+        Ok(())
+    }
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub enum Bool {
     Yes,
@@ -187,7 +392,7 @@ impl Bool {
     }
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
 #[allow(non_camel_case_types)]
 pub enum DataType {
     ui1,
@@ -243,18 +448,162 @@ const fn one() -> i32 {
 }
 
 impl SCPD {
+    /// Fetches and parses an SCPD document from `uri` over the default,
+    /// `hyper`-backed [`HyperTransport`](crate::transport::HyperTransport).
     pub async fn from_url(uri: hyper::Uri, urn: String) -> Result<Self, Error> {
-        let client = hyper::Client::new();
+        Self::from_url_with(&crate::transport::HyperTransport::new(), uri, urn).await
+    }
 
-        let body = client
-            .get(uri)
-            .and_then(|response| response.into_body().concat2())
-            .map_err(Error::NetworkError)
-            .compat()
-            .await?;
+    /// Same as [`SCPD::from_url`], but over a caller-supplied [`Transport`]
+    /// (a custom client, TLS config, or a test double).
+    pub async fn from_url_with<T: Transport>(
+        transport: &T,
+        uri: hyper::Uri,
+        urn: String,
+    ) -> Result<Self, Error> {
+        let body = transport.fetch(uri).await?;
 
         let mut scpd: SCPD = serde_xml_rs::from_reader(&body[..])?;
         scpd.urn = urn;
         Ok(scpd)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn action_fixture() -> Action {
+        serde_xml_rs::from_str(
+            r#"<action>
+                <name>SetVolume</name>
+                <argumentList>
+                    <argument>
+                        <name>Channel</name>
+                        <direction>in</direction>
+                        <relatedStateVariable>A_ARG_TYPE_Channel</relatedStateVariable>
+                    </argument>
+                    <argument>
+                        <name>DesiredVolume</name>
+                        <direction>in</direction>
+                        <relatedStateVariable>Volume</relatedStateVariable>
+                    </argument>
+                    <argument>
+                        <name>CurrentVolume</name>
+                        <direction>out</direction>
+                        <relatedStateVariable>Volume</relatedStateVariable>
+                    </argument>
+                </argumentList>
+            </action>"#,
+        )
+        .expect("fixture action parses")
+    }
+
+    fn state_variables_fixture() -> Vec<StateVariable> {
+        vec![
+            serde_xml_rs::from_str(
+                r#"<stateVariable>
+                    <name>A_ARG_TYPE_Channel</name>
+                    <dataType>string</dataType>
+                </stateVariable>"#,
+            )
+            .expect("fixture state variable parses"),
+            serde_xml_rs::from_str(
+                r#"<stateVariable>
+                    <name>Volume</name>
+                    <dataType>ui4</dataType>
+                    <allowedValueRange>
+                        <minimum>0</minimum>
+                        <maximum>100</maximum>
+                        <step>1</step>
+                    </allowedValueRange>
+                </stateVariable>"#,
+            )
+            .expect("fixture state variable parses"),
+        ]
+    }
+
+    #[test]
+    fn validate_arguments_rejects_missing_argument() {
+        let action = action_fixture();
+        let state_variables = state_variables_fixture();
+
+        let err = action
+            .validate_arguments(&state_variables, &[("Channel", ArgumentValue::Str("Master".to_string()))])
+            .unwrap_err();
+
+        assert!(matches!(err, Error::MissingArgument(name) if name == "DesiredVolume"));
+    }
+
+    #[test]
+    fn validate_arguments_rejects_unexpected_argument() {
+        let action = action_fixture();
+        let state_variables = state_variables_fixture();
+
+        let err = action
+            .validate_arguments(
+                &state_variables,
+                &[
+                    ("Channel", ArgumentValue::Str("Master".to_string())),
+                    ("DesiredVolume", ArgumentValue::UI4(10)),
+                    ("Loudness", ArgumentValue::Boolean(Bool::Yes)),
+                ],
+            )
+            .unwrap_err();
+
+        assert!(matches!(err, Error::UnexpectedArgument(name) if name == "Loudness"));
+    }
+
+    #[test]
+    fn validate_arguments_accepts_well_typed_arguments() {
+        let action = action_fixture();
+        let state_variables = state_variables_fixture();
+
+        action
+            .validate_arguments(
+                &state_variables,
+                &[
+                    ("Channel", ArgumentValue::Str("Master".to_string())),
+                    ("DesiredVolume", ArgumentValue::UI4(10)),
+                ],
+            )
+            .expect("arguments match their state variables");
+    }
+
+    #[test]
+    fn state_variable_validate_rejects_type_mismatch() {
+        let state_variables = state_variables_fixture();
+        let volume = &state_variables[1];
+
+        let err = volume.validate(&ArgumentValue::Str("10".to_string())).unwrap_err();
+
+        assert!(matches!(err, Error::ArgumentTypeMismatch { .. }));
+    }
+
+    #[test]
+    fn state_variable_validate_enforces_allowed_value_range() {
+        let state_variables = state_variables_fixture();
+        let volume = &state_variables[1];
+
+        let err = volume.validate(&ArgumentValue::UI4(150)).unwrap_err();
+
+        assert!(matches!(err, Error::ArgumentOutOfRange { min: 0, max: 100, .. }));
+    }
+
+    #[test]
+    fn escape_xml_escapes_reserved_characters() {
+        assert_eq!(escape_xml("<a> & <b>"), "&lt;a&gt; &amp; &lt;b&gt;");
+    }
+
+    #[test]
+    fn build_envelope_escapes_argument_values() {
+        let action = action_fixture();
+        let envelope = action.build_envelope(
+            "urn:schemas-upnp-org:service:RenderingControl:1",
+            &[("Channel", ArgumentValue::Str("<Master>".to_string()))],
+        );
+
+        assert!(envelope.contains("<Channel>&lt;Master&gt;</Channel>"));
+        assert!(envelope.contains("<u:SetVolume xmlns:u=\"urn:schemas-upnp-org:service:RenderingControl:1\">"));
+    }
+}
@@ -0,0 +1,168 @@
+//! Pluggable HTTP transport.
+//!
+//! `SCPD::from_url`, `Action::call` and the eventing subsystem all need to
+//! speak HTTP, but callers may want their own client, TLS config, timeouts,
+//! or a test double. [`Transport`] is the seam: implementors only need to
+//! answer `fetch` and `request_with_headers` (`request` and `post` have
+//! default implementations on top of it), and [`HyperTransport`] is the default, `hyper`-backed
+//! implementation used when a caller doesn't supply their own.
+
+use crate::Error;
+use async_trait::async_trait;
+use bytes::Bytes;
+use hyper::{Body, Client, HeaderMap, Method, Request, Uri};
+
+/// A minimal HTTP transport: fetch a resource, or post a body and get the
+/// response back as raw bytes.
+#[async_trait]
+pub trait Transport: Send + Sync {
+    /// `GET`s `uri` and returns the response body.
+    async fn fetch(&self, uri: Uri) -> Result<Bytes, Error>;
+
+    /// Issues `method` against `uri` with `headers` and `body`, returning the
+    /// response headers and body. GENA's `SUBSCRIBE` (initial and renewal)
+    /// needs the `SID`/`TIMEOUT` response headers, which a body-only call
+    /// would throw away.
+    async fn request_with_headers(
+        &self,
+        method: Method,
+        uri: Uri,
+        headers: &[(&str, String)],
+        body: Vec<u8>,
+    ) -> Result<(HeaderMap, Bytes), Error>;
+
+    /// Like [`Transport::request_with_headers`], but discards the response
+    /// headers. Used for the SOAP control path, which only needs the body.
+    async fn request(
+        &self,
+        method: Method,
+        uri: Uri,
+        headers: &[(&str, String)],
+        body: Vec<u8>,
+    ) -> Result<Bytes, Error> {
+        self.request_with_headers(method, uri, headers, body)
+            .await
+            .map(|(_, body)| body)
+    }
+
+    /// Convenience wrapper over [`Transport::request`] for a plain `POST`.
+    async fn post(&self, uri: Uri, headers: &[(&str, String)], body: Vec<u8>) -> Result<Bytes, Error> {
+        self.request(Method::POST, uri, headers, body).await
+    }
+}
+
+/// The default [`Transport`], backed by a shared `hyper::Client` on std
+/// futures (no `futures01`/`compat` bridging).
+#[derive(Clone, Default)]
+pub struct HyperTransport {
+    client: Client<hyper::client::HttpConnector>,
+}
+
+impl HyperTransport {
+    pub fn new() -> Self {
+        HyperTransport {
+            client: Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl Transport for HyperTransport {
+    async fn fetch(&self, uri: Uri) -> Result<Bytes, Error> {
+        let response = self.client.get(uri).await.map_err(Error::NetworkError)?;
+        hyper::body::to_bytes(response.into_body())
+            .await
+            .map_err(Error::NetworkError)
+    }
+
+    async fn request_with_headers(
+        &self,
+        method: Method,
+        uri: Uri,
+        headers: &[(&str, String)],
+        body: Vec<u8>,
+    ) -> Result<(HeaderMap, Bytes), Error> {
+        let mut builder = Request::builder().method(method).uri(uri);
+        for (name, value) in headers {
+            builder = builder.header(*name, value.as_str());
+        }
+        let request = builder
+            .body(Body::from(body))
+            .map_err(Error::InvalidRequest)?;
+
+        let response = self.client.request(request).await.map_err(Error::NetworkError)?;
+        let response_headers = response.headers().clone();
+        let body = hyper::body::to_bytes(response.into_body())
+            .await
+            .map_err(Error::NetworkError)?;
+        Ok((response_headers, body))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Records every `request_with_headers` call instead of making one, so
+    /// the default `request`/`post` methods can be tested without a live
+    /// server.
+    #[derive(Default)]
+    struct RecordingTransport {
+        calls: Mutex<Vec<(Method, Uri)>>,
+    }
+
+    #[async_trait]
+    impl Transport for RecordingTransport {
+        async fn fetch(&self, _uri: Uri) -> Result<Bytes, Error> {
+            unreachable!("not exercised by these tests")
+        }
+
+        async fn request_with_headers(
+            &self,
+            method: Method,
+            uri: Uri,
+            _headers: &[(&str, String)],
+            body: Vec<u8>,
+        ) -> Result<(HeaderMap, Bytes), Error> {
+            self.calls.lock().unwrap().push((method, uri));
+            Ok((HeaderMap::new(), Bytes::from(body)))
+        }
+    }
+
+    #[tokio::test]
+    async fn request_discards_the_response_headers() {
+        let transport = RecordingTransport::default();
+
+        let body = transport
+            .request(
+                Method::GET,
+                "http://example.com/".parse().unwrap(),
+                &[],
+                b"hello".to_vec(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(body, Bytes::from_static(b"hello"));
+        assert_eq!(transport.calls.lock().unwrap()[0].0, Method::GET);
+    }
+
+    #[tokio::test]
+    async fn post_issues_a_post_request_to_the_given_uri() {
+        let transport = RecordingTransport::default();
+
+        transport
+            .post(
+                "http://example.com/control".parse().unwrap(),
+                &[],
+                b"<soap/>".to_vec(),
+            )
+            .await
+            .unwrap();
+
+        let calls = transport.calls.lock().unwrap();
+        assert_eq!(calls[0].0, Method::POST);
+        assert_eq!(calls[0].1, "http://example.com/control".parse::<Uri>().unwrap());
+    }
+}
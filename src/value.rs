@@ -0,0 +1,381 @@
+//! Wire-level representation of UPnP action arguments and state-variable
+//! values.
+//!
+//! [`Value`] covers every [`DataType`](crate::scpd::DataType) variant, so
+//! `StateVariable::data_type_str` never has to fall back to
+//! `unimplemented!()`.
+
+use crate::scpd::{Bool, DataType};
+use crate::Error;
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime};
+
+/// A UPnP `fixed14.4`: up to 14 integer digits and 4 fractional digits,
+/// stored as a sign plus the whole part and ten-thousandths so no precision
+/// is lost converting to and from the wire format.
+///
+/// The sign is tracked explicitly rather than folded into `integer` because
+/// `i64` can't represent a negative zero — values in `(-1, 0)`, e.g.
+/// `"-0.5"`, would otherwise parse back as positive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fixed14_4 {
+    pub negative: bool,
+    pub integer: u64,
+    pub fraction: u16,
+}
+
+impl std::fmt::Display for Fixed14_4 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}{}.{:04}",
+            if self.negative { "-" } else { "" },
+            self.integer,
+            self.fraction
+        )
+    }
+}
+
+/// A typed UPnP argument or state-variable value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    UI1(u8),
+    UI2(u16),
+    UI4(u32),
+    UI8(u64),
+    I1(i8),
+    I2(i16),
+    I4(i32),
+    Int(i64),
+    R4(f32),
+    R8(f64),
+    Number(f64),
+    Float(f32),
+    Fixed14_4(Fixed14_4),
+    Char(char),
+    Str(String),
+    Date(NaiveDate),
+    DateTime(NaiveDateTime),
+    DateTimeTz(DateTime<FixedOffset>),
+    Time(NaiveTime),
+    TimeTz(NaiveTime, FixedOffset),
+    Boolean(Bool),
+    BinBase64(Vec<u8>),
+    BinHex(Vec<u8>),
+    Uri(hyper::Uri),
+}
+
+impl Value {
+    /// The [`DataType`] this value was constructed as.
+    pub fn data_type(&self) -> DataType {
+        match self {
+            Value::UI1(_) => DataType::ui1,
+            Value::UI2(_) => DataType::ui2,
+            Value::UI4(_) => DataType::ui4,
+            Value::UI8(_) => DataType::ui8,
+            Value::I1(_) => DataType::i1,
+            Value::I2(_) => DataType::i2,
+            Value::I4(_) => DataType::i4,
+            Value::Int(_) => DataType::int,
+            Value::R4(_) => DataType::r4,
+            Value::R8(_) => DataType::r8,
+            Value::Number(_) => DataType::number,
+            Value::Float(_) => DataType::float,
+            Value::Fixed14_4(_) => DataType::fixed14_4,
+            Value::Char(_) => DataType::char,
+            Value::Str(_) => DataType::string,
+            Value::Date(_) => DataType::date,
+            Value::DateTime(_) => DataType::dateTime,
+            Value::DateTimeTz(_) => DataType::dateTimeTz,
+            Value::Time(_) => DataType::time,
+            Value::TimeTz(..) => DataType::timeTz,
+            Value::Boolean(_) => DataType::boolean,
+            Value::BinBase64(_) => DataType::binBase64,
+            Value::BinHex(_) => DataType::binHex,
+            Value::Uri(_) => DataType::uri,
+        }
+    }
+
+    /// This value's signed integer reading, for the data types for which
+    /// `AllowedValueRange` makes sense.
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Value::UI1(n) => Some(*n as i64),
+            Value::UI2(n) => Some(*n as i64),
+            Value::UI4(n) => Some(*n as i64),
+            Value::UI8(n) => Some(*n as i64),
+            Value::I1(n) => Some(*n as i64),
+            Value::I2(n) => Some(*n as i64),
+            Value::I4(n) => Some(*n as i64),
+            Value::Int(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    /// Renders this value the way a device expects to see it on the wire.
+    pub fn to_wire_string(&self) -> String {
+        match self {
+            Value::UI1(n) => n.to_string(),
+            Value::UI2(n) => n.to_string(),
+            Value::UI4(n) => n.to_string(),
+            Value::UI8(n) => n.to_string(),
+            Value::I1(n) => n.to_string(),
+            Value::I2(n) => n.to_string(),
+            Value::I4(n) => n.to_string(),
+            Value::Int(n) => n.to_string(),
+            Value::R4(n) => n.to_string(),
+            Value::R8(n) => n.to_string(),
+            Value::Number(n) => n.to_string(),
+            Value::Float(n) => n.to_string(),
+            Value::Fixed14_4(n) => n.to_string(),
+            Value::Char(c) => c.to_string(),
+            Value::Str(s) => s.clone(),
+            Value::Date(d) => d.format("%Y-%m-%d").to_string(),
+            Value::DateTime(dt) => dt.format("%Y-%m-%dT%H:%M:%S").to_string(),
+            Value::DateTimeTz(dt) => dt.to_rfc3339(),
+            Value::Time(t) => t.format("%H:%M:%S").to_string(),
+            Value::TimeTz(t, offset) => format!("{}{}", t.format("%H:%M:%S"), offset),
+            Value::Boolean(Bool::Yes) => "1".to_string(),
+            Value::Boolean(Bool::No) => "0".to_string(),
+            Value::BinBase64(bytes) => base64::encode(bytes),
+            Value::BinHex(bytes) => hex::encode(bytes),
+            Value::Uri(uri) => uri.to_string(),
+        }
+    }
+
+    /// Parses a wire-format string into the `Value` matching `data_type`.
+    pub fn from_wire_str(data_type: DataType, raw: &str) -> Result<Value, Error> {
+        match data_type {
+            DataType::ui1 => raw.parse().map(Value::UI1).map_err(Error::from),
+            DataType::ui2 => raw.parse().map(Value::UI2).map_err(Error::from),
+            DataType::ui4 => raw.parse().map(Value::UI4).map_err(Error::from),
+            DataType::ui8 => raw.parse().map(Value::UI8).map_err(Error::from),
+            DataType::i1 => raw.parse().map(Value::I1).map_err(Error::from),
+            DataType::i2 => raw.parse().map(Value::I2).map_err(Error::from),
+            DataType::i4 => raw.parse().map(Value::I4).map_err(Error::from),
+            DataType::int => raw.parse().map(Value::Int).map_err(Error::from),
+            DataType::r4 => raw.parse().map(Value::R4).map_err(Error::from),
+            DataType::r8 => raw.parse().map(Value::R8).map_err(Error::from),
+            DataType::number => raw.parse().map(Value::Number).map_err(Error::from),
+            DataType::float => raw.parse().map(Value::Float).map_err(Error::from),
+            DataType::fixed14_4 => parse_fixed14_4(raw).map(Value::Fixed14_4),
+            DataType::char => raw
+                .chars()
+                .next()
+                .map(Value::Char)
+                .ok_or_else(|| Error::InvalidValue(raw.to_string())),
+            DataType::string => Ok(Value::Str(raw.to_string())),
+            DataType::date => NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+                .map(Value::Date)
+                .map_err(|_| Error::InvalidValue(raw.to_string())),
+            DataType::dateTime => parse_date_time(raw)
+                .map(Value::DateTime)
+                .map_err(|_| Error::InvalidValue(raw.to_string())),
+            DataType::dateTimeTz => DateTime::parse_from_rfc3339(raw)
+                .map(Value::DateTimeTz)
+                .map_err(|_| Error::InvalidValue(raw.to_string())),
+            DataType::time => NaiveTime::parse_from_str(raw, "%H:%M:%S")
+                .map(Value::Time)
+                .map_err(|_| Error::InvalidValue(raw.to_string())),
+            DataType::timeTz => parse_time_tz(raw).ok_or_else(|| Error::InvalidValue(raw.to_string())),
+            DataType::boolean => match raw {
+                "1" | "true" | "yes" => Ok(Value::Boolean(Bool::Yes)),
+                "0" | "false" | "no" => Ok(Value::Boolean(Bool::No)),
+                _ => Err(Error::InvalidValue(raw.to_string())),
+            },
+            DataType::binBase64 => base64::decode(raw)
+                .map(Value::BinBase64)
+                .map_err(|_| Error::InvalidValue(raw.to_string())),
+            DataType::binHex => hex::decode(raw)
+                .map(Value::BinHex)
+                .map_err(|_| Error::InvalidValue(raw.to_string())),
+            DataType::uri => raw
+                .parse()
+                .map(Value::Uri)
+                .map_err(|_| Error::InvalidValue(raw.to_string())),
+        }
+    }
+}
+
+/// Parses a `fixed14.4`, e.g. `"-123.4500"` or `"-0.5"`, into its sign plus
+/// whole and fractional (ten-thousandths) parts. The sign is read off the raw
+/// string up front so a zero whole part doesn't lose it.
+fn parse_fixed14_4(raw: &str) -> Result<Fixed14_4, Error> {
+    let negative = raw.starts_with('-');
+    let magnitude = raw.strip_prefix('-').unwrap_or(raw);
+
+    let (whole, frac) = magnitude.split_once('.').unwrap_or((magnitude, "0"));
+    let integer: u64 = whole.parse().map_err(|_| Error::InvalidValue(raw.to_string()))?;
+    let frac_digits = format!("{:0<4}", frac);
+    let fraction: u16 = frac_digits
+        .get(..4)
+        .unwrap_or(&frac_digits)
+        .parse()
+        .map_err(|_| Error::InvalidValue(raw.to_string()))?;
+    Ok(Fixed14_4 {
+        negative,
+        integer,
+        fraction,
+    })
+}
+
+/// `dateTime` allows either a date or a full `%Y-%m-%dT%H:%M:%S` timestamp.
+fn parse_date_time(raw: &str) -> chrono::ParseResult<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(raw, "%Y-%m-%dT%H:%M:%S")
+        .or_else(|_| NaiveDate::parse_from_str(raw, "%Y-%m-%d").map(|d| d.and_hms(0, 0, 0)))
+}
+
+fn parse_time_tz(raw: &str) -> Option<Value> {
+    let split_at = raw.find(|c| c == '+' || c == '-' || c == 'Z')?;
+    let (time_part, offset_part) = raw.split_at(split_at);
+    let time = NaiveTime::parse_from_str(time_part, "%H:%M:%S").ok()?;
+    let offset = if offset_part == "Z" {
+        FixedOffset::east(0)
+    } else {
+        DateTime::parse_from_str(&format!("1970-01-01T00:00:00{}", offset_part), "%Y-%m-%dT%H:%M:%S%:z")
+            .ok()?
+            .offset()
+            .to_owned()
+    };
+    Some(Value::TimeTz(time, offset))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trips(data_type: DataType, raw: &str) {
+        let value = Value::from_wire_str(data_type, raw).expect("parses");
+        assert_eq!(value.data_type(), data_type);
+        assert_eq!(value.to_wire_string(), raw);
+    }
+
+    #[test]
+    fn round_trips_integers() {
+        round_trips(DataType::ui1, "255");
+        round_trips(DataType::i4, "-1234");
+        round_trips(DataType::int, "-9223372036854775808");
+    }
+
+    #[test]
+    fn round_trips_string_and_char() {
+        round_trips(DataType::string, "hello world");
+        round_trips(DataType::char, "x");
+    }
+
+    #[test]
+    fn round_trips_uri() {
+        round_trips(DataType::uri, "http://example.com/foo");
+    }
+
+    #[test]
+    fn round_trips_fixed14_4() {
+        round_trips(DataType::fixed14_4, "123.4500");
+        round_trips(DataType::fixed14_4, "-123.4500");
+        round_trips(DataType::fixed14_4, "-0.5000");
+    }
+
+    #[test]
+    fn round_trips_date_and_time_types() {
+        round_trips(DataType::date, "2024-01-02");
+        round_trips(DataType::dateTime, "2024-01-02T03:04:05");
+        round_trips(DataType::time, "03:04:05");
+    }
+
+    #[test]
+    fn round_trips_date_time_tz() {
+        let value = Value::from_wire_str(DataType::dateTimeTz, "2024-01-02T03:04:05+02:00").unwrap();
+        assert_eq!(value.data_type(), DataType::dateTimeTz);
+        assert_eq!(value.to_wire_string(), "2024-01-02T03:04:05+02:00");
+    }
+
+    #[test]
+    fn round_trips_time_tz() {
+        let value = Value::from_wire_str(DataType::timeTz, "03:04:05+02:00").unwrap();
+        assert_eq!(value.data_type(), DataType::timeTz);
+        assert_eq!(value.to_wire_string(), "03:04:05+02:00");
+    }
+
+    #[test]
+    fn round_trips_binary() {
+        assert_eq!(
+            Value::from_wire_str(DataType::binBase64, "aGVsbG8=")
+                .unwrap()
+                .to_wire_string(),
+            "aGVsbG8="
+        );
+        assert_eq!(
+            Value::from_wire_str(DataType::binHex, "68656c6c6f")
+                .unwrap()
+                .to_wire_string(),
+            "68656c6c6f"
+        );
+    }
+
+    #[test]
+    fn boolean_accepts_device_and_canonical_spellings() {
+        assert_eq!(
+            Value::from_wire_str(DataType::boolean, "yes").unwrap(),
+            Value::Boolean(Bool::Yes)
+        );
+        assert_eq!(
+            Value::from_wire_str(DataType::boolean, "0").unwrap(),
+            Value::Boolean(Bool::No)
+        );
+        assert!(Value::from_wire_str(DataType::boolean, "maybe").is_err());
+    }
+
+    #[test]
+    fn parse_fixed14_4_splits_integer_and_fraction() {
+        assert_eq!(
+            parse_fixed14_4("-123.45").unwrap(),
+            Fixed14_4 {
+                negative: true,
+                integer: 123,
+                fraction: 4500
+            }
+        );
+        assert_eq!(
+            parse_fixed14_4("7").unwrap(),
+            Fixed14_4 {
+                negative: false,
+                integer: 7,
+                fraction: 0
+            }
+        );
+    }
+
+    #[test]
+    fn parse_fixed14_4_preserves_sign_of_a_negative_zero_whole_part() {
+        assert_eq!(
+            parse_fixed14_4("-0.5").unwrap(),
+            Fixed14_4 {
+                negative: true,
+                integer: 0,
+                fraction: 5000
+            }
+        );
+        assert_eq!(parse_fixed14_4("-0.5").unwrap().to_string(), "-0.5000");
+    }
+
+    #[test]
+    fn parse_fixed14_4_rejects_garbage() {
+        assert!(parse_fixed14_4("not-a-number").is_err());
+    }
+
+    #[test]
+    fn parse_time_tz_handles_zulu_and_offset() {
+        let zulu = parse_time_tz("03:04:05Z").unwrap();
+        assert_eq!(zulu, Value::TimeTz(NaiveTime::from_hms(3, 4, 5), FixedOffset::east(0)));
+
+        let offset = parse_time_tz("03:04:05+02:00").unwrap();
+        assert_eq!(
+            offset,
+            Value::TimeTz(NaiveTime::from_hms(3, 4, 5), FixedOffset::east(2 * 3600))
+        );
+    }
+
+    #[test]
+    fn parse_time_tz_rejects_missing_offset() {
+        assert!(parse_time_tz("03:04:05").is_none());
+    }
+}